@@ -1,7 +1,11 @@
 /*!
 WARNING: This signature software is a prototype. It has been replaced by the final system
 [Ed25519](http://ed25519.cr.yp.to/). It is only kept here for compatibility reasons.
+
+Use `crypto::sign::ed25519` instead.
 */
+#![deprecated = "superseded by crypto::sign::ed25519"]
+
 use libc::{c_ulonglong, c_int};
 use std::slice::from_elem;
 
@@ -0,0 +1,4 @@
+/*!
+Public-key signatures
+*/
+pub mod ed25519;